@@ -0,0 +1,659 @@
+extern crate alloc;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+
+use crate::error::{Error, Result};
+
+/// Deserialize `bytes` as canonical BCS into a value of type `T`.
+///
+/// This enforces the crate's default container-depth limit
+/// ([`crate::MAX_CONTAINER_DEPTH`]) and requires that the whole input be
+/// consumed.
+pub fn from_bytes<T>(bytes: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    from_bytes_with_limit(bytes, crate::MAX_CONTAINER_DEPTH)
+}
+
+/// Like [`from_bytes`], but with an explicit, caller-chosen container-depth
+/// limit (which must not exceed [`crate::MAX_CONTAINER_DEPTH`]).
+pub fn from_bytes_with_limit<T>(bytes: &[u8], limit: usize) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    if limit > crate::MAX_CONTAINER_DEPTH {
+        return Err(Error::NotSupported("limit exceeds the max allowed depth"));
+    }
+    let mut deserializer = Deserializer::new(bytes, limit);
+    let value = T::deserialize(&mut deserializer)?;
+    if !deserializer.is_empty() {
+        return Err(Error::RemainingInput(deserializer.remaining_len() as u64));
+    }
+    Ok(value)
+}
+
+pub(crate) struct Deserializer<'de> {
+    input: &'de [u8],
+    max_remaining_depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    pub(crate) fn new(input: &'de [u8], max_remaining_depth: usize) -> Self {
+        Self {
+            input,
+            max_remaining_depth,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub(crate) fn remaining_len(&self) -> usize {
+        self.input.len()
+    }
+
+    pub(crate) fn remaining(&self) -> &'de [u8] {
+        self.input
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        let byte = *self.input.first().ok_or(Error::Eof)?;
+        self.input = &self.input[1..];
+        Ok(byte)
+    }
+
+    fn next_bytes(&mut self, len: usize) -> Result<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(Error::Eof);
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn parse_bool(&mut self) -> Result<bool> {
+        match self.next_byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::ExpectedBoolean),
+        }
+    }
+
+    pub(crate) fn parse_option_tag(&mut self) -> Result<bool> {
+        match self.next_byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::ExpectedOption),
+        }
+    }
+
+    pub(crate) fn parse_u8(&mut self) -> Result<u8> {
+        self.next_byte()
+    }
+
+    pub(crate) fn parse_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.next_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn parse_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.next_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn parse_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.next_bytes(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn parse_u128(&mut self) -> Result<u128> {
+        Ok(u128::from_le_bytes(self.next_bytes(16)?.try_into().unwrap()))
+    }
+
+    // Reads a ULEB128-encoded value, accumulating 7 bits per byte. Rejects
+    // encodings that overflow 32 bits and encodings that are not minimal
+    // (a final byte whose payload is zero, which means a shorter encoding
+    // would have represented the same value).
+    fn parse_uleb128_as_u32(&mut self) -> Result<u32> {
+        let mut value: u64 = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.next_byte()?;
+            let digit = u64::from(byte & 0x7f);
+            value |= digit << shift;
+            if value > u64::from(u32::MAX) {
+                return Err(Error::IntegerOverflowDuringUleb128Decoding);
+            }
+            if byte & 0x80 == 0 {
+                if shift > 0 && digit == 0 {
+                    return Err(Error::NonCanonicalUleb128Encoding);
+                }
+                return Ok(value as u32);
+            }
+        }
+        Err(Error::IntegerOverflowDuringUleb128Decoding)
+    }
+
+    pub(crate) fn parse_variant_index(&mut self) -> Result<u32> {
+        self.parse_uleb128_as_u32()
+    }
+
+    pub(crate) fn parse_length(&mut self) -> Result<usize> {
+        let len = self.parse_uleb128_as_u32()? as usize;
+        if len > crate::MAX_SEQUENCE_LENGTH {
+            return Err(Error::ExceededMaxLen(len));
+        }
+        Ok(len)
+    }
+
+    pub(crate) fn parse_bytes(&mut self) -> Result<&'de [u8]> {
+        let len = self.parse_length()?;
+        self.next_bytes(len)
+    }
+
+    pub(crate) fn parse_str(&mut self) -> Result<&'de str> {
+        core::str::from_utf8(self.parse_bytes()?).map_err(|_| Error::Utf8)
+    }
+
+    pub(crate) fn enter_named_container(&mut self, name: &'static str) -> Result<()> {
+        if self.max_remaining_depth == 0 {
+            return Err(Error::ExceededContainerDepthLimit(name));
+        }
+        self.max_remaining_depth -= 1;
+        Ok(())
+    }
+
+    // Restores the depth budget spent by the matching `enter_named_container`
+    // call, so the limit reflects how deeply containers are nested rather
+    // than how many have been entered in total over the whole input.
+    pub(crate) fn leave_named_container(&mut self) {
+        self.max_remaining_depth += 1;
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::NotSupported("deserialize_any"))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_bool(self.parse_bool()?)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i8(self.next_byte()? as i8)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i16(self.parse_u16()? as i16)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i32(self.parse_u32()? as i32)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i64(self.parse_u64()? as i64)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_u128()? as i128)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u8(self.next_byte()?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u16(self.parse_u16()?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u32(self.parse_u32()?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u64(self.parse_u64()?)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse_u128()?)
+    }
+
+    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::NotSupported("deserialize_f32"))
+    }
+
+    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::NotSupported("deserialize_f64"))
+    }
+
+    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::NotSupported("deserialize_char"))
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.parse_str()?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.parse_str()?)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.parse_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.parse_bytes()?)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.parse_option_tag()? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter_named_container(name)?;
+        let result = visitor.visit_unit();
+        self.leave_named_container();
+        result
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter_named_container(name)?;
+        let result = visitor.visit_newtype_struct(&mut *self);
+        self.leave_named_container();
+        result
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let len = self.parse_length()?;
+        visitor.visit_seq(SeqDeserializer {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter_named_container(name)?;
+        let result = visitor.visit_seq(SeqDeserializer {
+            de: &mut *self,
+            remaining: len,
+        });
+        self.leave_named_container();
+        result
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let len = self.parse_length()?;
+        visitor.visit_map(MapDeserializer {
+            de: self,
+            remaining: len,
+            previous_key: None,
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter_named_container(name)?;
+        let result = visitor.visit_seq(SeqDeserializer {
+            de: &mut *self,
+            remaining: fields.len(),
+        });
+        self.leave_named_container();
+        result
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.enter_named_container(name)?;
+        let result = visitor.visit_enum(&mut *self);
+        self.leave_named_container();
+        result
+    }
+
+    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::NotSupported("deserialize_identifier"))
+    }
+
+    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::NotSupported("deserialize_ignored_any"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct SeqDeserializer<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+struct MapDeserializer<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+    previous_key: Option<Vec<u8>>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for MapDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let before = self.de.input;
+        let key = seed.deserialize(&mut *self.de)?;
+        let key_bytes = &before[..before.len() - self.de.input.len()];
+
+        if matches!(&self.previous_key, Some(previous_key) if key_bytes <= previous_key.as_slice()) {
+            return Err(Error::NonCanonicalMap);
+        }
+        self.previous_key = Some(key_bytes.to_vec());
+
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant_index = self.parse_variant_index()?;
+        let value = seed.deserialize(variant_index.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Serialize};
+
+    // A container-depth limit that only budgets for nesting depth, not for
+    // how many sibling containers appear at the same level, should let a
+    // wide-but-shallow document round-trip regardless of its width.
+    #[test]
+    fn wide_sibling_structs_round_trip() {
+        #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+        struct S(u8);
+
+        let original: Vec<S> = (0..600).map(|i| S(i as u8)).collect();
+        let bytes = crate::to_bytes(&original).unwrap();
+        let decoded: Vec<S> = from_bytes(&bytes).unwrap();
+        assert_eq!(original, decoded);
+    }
+
+    #[test]
+    fn round_trips_a_mix_of_types() {
+        #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+
+        let original = (
+            Point { x: 1, y: 2 },
+            Vec::from([1u8, 2, 3]),
+            Some(42u64),
+            BTreeMap::from([(1u8, String::from("a")), (2u8, String::from("b"))]),
+        );
+        let bytes = crate::to_bytes(&original).unwrap();
+        let decoded: (Point, Vec<u8>, Option<u64>, BTreeMap<u8, String>) =
+            from_bytes(&bytes).unwrap();
+        assert_eq!(original, decoded);
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        // A single-byte bool followed by an extra, unconsumed byte.
+        assert_eq!(
+            from_bytes::<bool>(&[0u8, 1u8]),
+            Err(Error::RemainingInput(1))
+        );
+    }
+
+    #[test]
+    fn from_bytes_with_limit_rejects_limits_above_the_crate_max() {
+        assert_eq!(
+            from_bytes_with_limit::<u8>(&[1u8], crate::MAX_CONTAINER_DEPTH + 1),
+            Err(Error::NotSupported("limit exceeds the max allowed depth"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_bool_tag() {
+        assert_eq!(from_bytes::<bool>(&[2u8]), Err(Error::ExpectedBoolean));
+    }
+
+    #[test]
+    fn rejects_invalid_option_tag() {
+        assert_eq!(from_bytes::<Option<u8>>(&[2u8, 0u8]), Err(Error::ExpectedOption));
+    }
+
+    #[test]
+    fn rejects_non_minimal_uleb128_length() {
+        // A zero length re-encoded in two bytes instead of one.
+        assert_eq!(
+            from_bytes::<Vec<u8>>(&[0x80, 0x00]),
+            Err(Error::NonCanonicalUleb128Encoding)
+        );
+    }
+
+    #[test]
+    fn rejects_uleb128_overflowing_u32() {
+        assert_eq!(
+            from_bytes::<Vec<u8>>(&[0xff, 0xff, 0xff, 0xff, 0xff]),
+            Err(Error::IntegerOverflowDuringUleb128Decoding)
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_map_on_the_deserialize_side() {
+        // length = 2, then two entries with the same key (1), which breaks
+        // the strictly-increasing-key-bytes invariant that to_bytes upholds.
+        let bytes = [0x02, 0x01, 0x10, 0x01, 0x14];
+        assert_eq!(
+            from_bytes::<BTreeMap<u8, u8>>(&bytes),
+            Err(Error::NonCanonicalMap)
+        );
+    }
+}
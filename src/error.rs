@@ -1,11 +1,9 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
-#![no_std]
 
 extern crate alloc;
 use alloc::string::String;
 use core::fmt;
-use core::fmt::{Display, Formatter};
 use serde::{de, ser};
 use serde::de::StdError;
 
@@ -29,6 +27,7 @@ pub enum Error {
     NonCanonicalUleb128Encoding,
     IntegerOverflowDuringUleb128Decoding,
     BufferFull,
+    UnknownVariantIndex(u32),
 }
 
 impl fmt::Display for Error {
@@ -49,7 +48,7 @@ impl fmt::Display for Error {
             Error::Custom(msg) => write!(f, "{}", msg),
             Error::MissingLen => write!(f, "sequence missing length"),
             Error::NotSupported(feature) => write!(f, "not supported: {}", feature),
-            Error::RemainingInput(size) => write!(f, "remaining input"),
+            Error::RemainingInput(size) => write!(f, "remaining input: {} byte(s)", size),
             Error::Utf8 => write!(f, "malformed utf8"),
             Error::NonCanonicalUleb128Encoding => {
                 write!(f, "ULEB128 encoding was not minimal in size")
@@ -58,6 +57,9 @@ impl fmt::Display for Error {
                 write!(f, "ULEB128-encoded integer did not fit in the target size")
             }
             Error::BufferFull => write!(f, "output buffer is full"),
+            Error::UnknownVariantIndex(index) => {
+                write!(f, "enum variant index {} is out of range for the given format", index)
+            }
         }
     }
 }
@@ -0,0 +1,27 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+#![no_std]
+
+extern crate alloc;
+
+mod de;
+mod error;
+mod ser;
+mod value;
+
+pub use crate::{
+    de::{from_bytes, from_bytes_with_limit},
+    error::{Error, Result},
+    ser::{
+        serialize_into, serialized_size, serialized_size_with_limit, to_bytes, to_bytes_into,
+        to_bytes_strict, to_bytes_with_limit, BcsWrite,
+    },
+    value::{trace_deserialize, Format, Value},
+};
+
+/// The maximum length, in bytes, of any serialized sequence/map/string/bytes.
+pub const MAX_SEQUENCE_LENGTH: usize = (1 << 31) - 1;
+
+/// The maximum number of nested containers (structs, enums, etc.) that
+/// `to_bytes`/`from_bytes` will descend into before giving up.
+pub const MAX_CONTAINER_DEPTH: usize = 500;
@@ -1,8 +1,5 @@
-#![no_std]
-
 extern crate alloc;
 use alloc::vec::Vec;
-use core::convert::TryFrom;
 use serde::{ser, Serialize};
 
 use crate::error::{Error, Result};
@@ -17,6 +14,34 @@ where
     Ok(output)
 }
 
+/// Like [`to_bytes`], but returns `Error::NonCanonicalMap` if any
+/// serialized map contains two equal keys, instead of silently collapsing
+/// them during the canonical sort pass. Use this when the in-memory map is
+/// expected to already be canonical and a duplicate would indicate a bug
+/// (e.g. signature or consensus-critical code).
+pub fn to_bytes_strict<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = Vec::new();
+    let mut serializer = Serializer::new(&mut output, crate::MAX_CONTAINER_DEPTH);
+    serializer.strict_maps = true;
+    value.serialize(serializer)?;
+    Ok(output)
+}
+
+/// Serialize `value` as canonical BCS straight into `writer`, without ever
+/// materializing a `Vec<u8>` of the whole output. Useful for feeding bytes
+/// incrementally into a sink such as a cryptographic hasher.
+pub fn serialize_into<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: ?Sized + BcsWrite,
+    T: ?Sized + Serialize,
+{
+    let serializer = Serializer::new(writer, crate::MAX_CONTAINER_DEPTH);
+    value.serialize(serializer)
+}
+
 pub fn to_bytes_with_limit<T>(value: &T, limit: usize) -> Result<Vec<u8>>
 where
     T: ?Sized + Serialize,
@@ -53,8 +78,28 @@ where
     Ok(counter.0)
 }
 
-// Simple write trait for no_std
-trait BcsWrite {
+/// Serialize `value` as canonical BCS directly into `buf`, without
+/// allocating. Returns the number of bytes written, so the caller (who
+/// typically sized `buf` using [`serialized_size`]) can reslice it.
+///
+/// Returns `Error::BufferFull` if `buf` is too small; `buf` may have been
+/// partially written to in that case.
+pub fn to_bytes_into<T>(value: &T, buf: &mut [u8]) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = SliceWriter::new(buf);
+    let serializer = Serializer::new(&mut writer, crate::MAX_CONTAINER_DEPTH);
+    value.serialize(serializer)?;
+    Ok(writer.position)
+}
+
+/// A minimal, no_std-friendly sink that BCS output can be written into.
+///
+/// Implement this for your own writer (e.g. a cryptographic hasher) to feed
+/// serialized bytes to it incrementally via [`serialize_into`], without
+/// first collecting them into a `Vec<u8>`.
+pub trait BcsWrite {
     fn write_all(&mut self, buf: &[u8]) -> Result<()>;
 }
 
@@ -74,9 +119,34 @@ impl BcsWrite for SizeCounter {
     }
 }
 
+/// Writes into a caller-owned `&mut [u8]` at a running cursor, for
+/// zero-allocation serialization. Never panics: a write that would run past
+/// the end of the slice returns `Error::BufferFull` instead.
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, position: 0 }
+    }
+}
+
+impl<'a> BcsWrite for SliceWriter<'a> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.position.checked_add(bytes.len()).ok_or(Error::BufferFull)?;
+        let dest = self.buf.get_mut(self.position..end).ok_or(Error::BufferFull)?;
+        dest.copy_from_slice(bytes);
+        self.position = end;
+        Ok(())
+    }
+}
+
 struct Serializer<'a, W: ?Sized> {
     output: &'a mut W,
     max_remaining_depth: usize,
+    strict_maps: bool,
 }
 
 impl<'a, W> Serializer<'a, W>
@@ -87,6 +157,31 @@ where
         Self {
             output,
             max_remaining_depth,
+            strict_maps: false,
+        }
+    }
+
+    /// A child serializer over the same output, inheriting this
+    /// serializer's remaining depth budget and map strictness.
+    fn fork(&mut self) -> Serializer<'_, W> {
+        Serializer {
+            output: self.output,
+            max_remaining_depth: self.max_remaining_depth,
+            strict_maps: self.strict_maps,
+        }
+    }
+
+    /// A child serializer over a different output (used to serialize a map
+    /// entry's key/value into its own buffer), inheriting this serializer's
+    /// remaining depth budget and map strictness.
+    fn entry_serializer<'b, O>(&self, output: &'b mut O) -> Serializer<'b, O>
+    where
+        O: ?Sized + BcsWrite,
+    {
+        Serializer {
+            output,
+            max_remaining_depth: self.max_remaining_depth,
+            strict_maps: self.strict_maps,
         }
     }
 
@@ -336,7 +431,7 @@ where
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(Serializer::new(self.output, self.max_remaining_depth))
+        value.serialize(self.fork())
     }
 
     fn end(self) -> Result<()> {
@@ -355,7 +450,7 @@ where
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(Serializer::new(self.output, self.max_remaining_depth))
+        value.serialize(self.fork())
     }
 
     fn end(self) -> Result<()> {
@@ -374,7 +469,7 @@ where
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(Serializer::new(self.output, self.max_remaining_depth))
+        value.serialize(self.fork())
     }
 
     fn end(self) -> Result<()> {
@@ -393,7 +488,7 @@ where
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(Serializer::new(self.output, self.max_remaining_depth))
+        value.serialize(self.fork())
     }
 
     fn end(self) -> Result<()> {
@@ -433,7 +528,7 @@ where
         }
 
         let mut output = Vec::new();
-        key.serialize(Serializer::new(&mut output, self.serializer.max_remaining_depth))?;
+        key.serialize(self.serializer.entry_serializer(&mut output))?;
         self.next_key = Some(output);
         Ok(())
     }
@@ -445,7 +540,7 @@ where
         match self.next_key.take() {
             Some(key) => {
                 let mut output = Vec::new();
-                value.serialize(Serializer::new(&mut output, self.serializer.max_remaining_depth))?;
+                value.serialize(self.serializer.entry_serializer(&mut output))?;
                 self.entries.push((key, output));
                 Ok(())
             }
@@ -461,18 +556,24 @@ where
         // Sort entries for canonical encoding
         self.entries.sort_by(|e1, e2| e1.0.cmp(&e2.0));
 
-        // Manual duplicate removal since we want to avoid depending on additional traits
-        let mut write_idx = 0;
-        for read_idx in 1..self.entries.len() {
-            if self.entries[write_idx].0 != self.entries[read_idx].0 {
-                write_idx += 1;
-                if write_idx != read_idx {
-                    self.entries.swap(write_idx, read_idx);
+        if self.serializer.strict_maps {
+            if self.entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                return Err(Error::NonCanonicalMap);
+            }
+        } else {
+            // Manual duplicate removal since we want to avoid depending on additional traits
+            let mut write_idx = 0;
+            for read_idx in 1..self.entries.len() {
+                if self.entries[write_idx].0 != self.entries[read_idx].0 {
+                    write_idx += 1;
+                    if write_idx != read_idx {
+                        self.entries.swap(write_idx, read_idx);
+                    }
                 }
             }
-        }
-        if !self.entries.is_empty() {
-            self.entries.truncate(write_idx + 1);
+            if !self.entries.is_empty() {
+                self.entries.truncate(write_idx + 1);
+            }
         }
 
         let len = self.entries.len();
@@ -498,7 +599,7 @@ where
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(Serializer::new(self.output, self.max_remaining_depth))
+        value.serialize(self.fork())
     }
 
     fn end(self) -> Result<()> {
@@ -517,10 +618,69 @@ where
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(Serializer::new(self.output, self.max_remaining_depth))
+        value.serialize(self.fork())
     }
 
     fn end(self) -> Result<()> {
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_bytes_into_writes_exactly_serialized_size_bytes() {
+        let value = (1u32, Vec::from([1u8, 2, 3]), Some(42u64));
+        let size = serialized_size(&value).unwrap();
+        let mut buf = alloc::vec![0u8; size];
+
+        let written = to_bytes_into(&value, &mut buf).unwrap();
+
+        assert_eq!(written, size);
+        assert_eq!(&buf[..written], to_bytes(&value).unwrap().as_slice());
+    }
+
+    #[test]
+    fn to_bytes_into_rejects_an_undersized_buffer() {
+        let value = (1u32, Vec::from([1u8, 2, 3]), Some(42u64));
+        let size = serialized_size(&value).unwrap();
+        let mut buf = alloc::vec![0u8; size - 1];
+
+        assert_eq!(to_bytes_into(&value, &mut buf), Err(Error::BufferFull));
+    }
+
+    // A map with a deliberately duplicate key, bypassing the usual guarantee
+    // that an in-memory Rust map can't already have one. Real callers would
+    // get this from e.g. deduplicating two partially-overlapping maps.
+    struct DuplicateKeyMap(Vec<(u8, u8)>);
+
+    impl Serialize for DuplicateKeyMap {
+        fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: ser::Serializer,
+        {
+            use ser::SerializeMap;
+            let mut map = serializer.serialize_map(Some(self.0.len()))?;
+            for (key, value) in &self.0 {
+                map.serialize_entry(key, value)?;
+            }
+            map.end()
+        }
+    }
+
+    #[test]
+    fn to_bytes_strict_rejects_duplicate_map_keys() {
+        let map = DuplicateKeyMap(Vec::from([(1u8, 10u8), (1u8, 20u8)]));
+        assert_eq!(to_bytes_strict(&map), Err(Error::NonCanonicalMap));
+    }
+
+    #[test]
+    fn to_bytes_collapses_duplicate_map_keys_keeping_the_first() {
+        let map = DuplicateKeyMap(Vec::from([(1u8, 10u8), (1u8, 20u8)]));
+        let collapsed = to_bytes(&map).unwrap();
+        let expected = to_bytes(&alloc::collections::BTreeMap::from([(1u8, 10u8)])).unwrap();
+        assert_eq!(collapsed, expected);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::de::Deserializer;
+use crate::error::{Error, Result};
+
+/// A schema describing how to read a BCS byte string, for use with
+/// [`trace_deserialize`] when the original Rust type isn't available (e.g. a
+/// debugging tool or a generic on-chain data viewer). BCS carries no type
+/// tags of its own, so the shape has to be supplied out of band.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Bool,
+    Bytes,
+    Option(Box<Format>),
+    Seq(Box<Format>),
+    Tuple(Vec<Format>),
+    Struct(Vec<Format>),
+    /// One `Format` per variant, selected by the ULEB128 variant index read
+    /// from the input.
+    Enum(Vec<Format>),
+    Map(Box<Format>, Box<Format>),
+}
+
+/// A dynamically typed BCS value, produced by [`trace_deserialize`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    Option(Option<Box<Value>>),
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    Struct(Vec<Value>),
+    Enum(u32, Box<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+/// Decode `bytes` as canonical BCS according to `format`, producing a
+/// dynamic [`Value`] tree instead of a concrete Rust type. This enforces the
+/// crate's default container-depth limit and requires that the whole input
+/// be consumed, exactly like [`crate::from_bytes`].
+pub fn trace_deserialize(format: &Format, bytes: &[u8]) -> Result<Value> {
+    let mut de = Deserializer::new(bytes, crate::MAX_CONTAINER_DEPTH);
+    let value = parse_value(&mut de, format)?;
+    if !de.is_empty() {
+        return Err(Error::RemainingInput(de.remaining_len() as u64));
+    }
+    Ok(value)
+}
+
+fn parse_value(de: &mut Deserializer, format: &Format) -> Result<Value> {
+    match format {
+        Format::U8 => Ok(Value::U8(de.parse_u8()?)),
+        Format::U16 => Ok(Value::U16(de.parse_u16()?)),
+        Format::U32 => Ok(Value::U32(de.parse_u32()?)),
+        Format::U64 => Ok(Value::U64(de.parse_u64()?)),
+        Format::U128 => Ok(Value::U128(de.parse_u128()?)),
+        Format::Bool => Ok(Value::Bool(de.parse_bool()?)),
+        Format::Bytes => Ok(Value::Bytes(de.parse_bytes()?.to_vec())),
+        Format::Option(inner) => {
+            if de.parse_option_tag()? {
+                Ok(Value::Option(Some(Box::new(parse_value(de, inner)?))))
+            } else {
+                Ok(Value::Option(None))
+            }
+        }
+        Format::Seq(element) => {
+            let len = de.parse_length()?;
+            // Don't pre-allocate for `len`: it comes straight from the
+            // input and hasn't been checked against what's actually left
+            // to read, so a tiny malicious input could otherwise request
+            // an enormous allocation.
+            let mut values = Vec::new();
+            for _ in 0..len {
+                values.push(parse_value(de, element)?);
+            }
+            Ok(Value::Seq(values))
+        }
+        Format::Tuple(elements) => {
+            let mut values = Vec::with_capacity(elements.len());
+            for element in elements {
+                values.push(parse_value(de, element)?);
+            }
+            Ok(Value::Tuple(values))
+        }
+        Format::Struct(fields) => {
+            de.enter_named_container("struct")?;
+            let mut values = Vec::with_capacity(fields.len());
+            for field in fields {
+                values.push(parse_value(de, field)?);
+            }
+            de.leave_named_container();
+            Ok(Value::Struct(values))
+        }
+        Format::Enum(variants) => {
+            de.enter_named_container("enum")?;
+            let index = de.parse_variant_index()?;
+            let variant_format = variants
+                .get(index as usize)
+                .ok_or(Error::UnknownVariantIndex(index))?;
+            let value = parse_value(de, variant_format)?;
+            de.leave_named_container();
+            Ok(Value::Enum(index, Box::new(value)))
+        }
+        Format::Map(key_format, value_format) => {
+            let len = de.parse_length()?;
+            // See the `Seq` arm above for why this isn't `with_capacity(len)`.
+            let mut entries = Vec::new();
+            let mut previous_key_bytes: Option<Vec<u8>> = None;
+            for _ in 0..len {
+                let before = de.remaining();
+                let key = parse_value(de, key_format)?;
+                let key_bytes = &before[..before.len() - de.remaining_len()];
+                if matches!(&previous_key_bytes, Some(previous) if key_bytes <= previous.as_slice())
+                {
+                    return Err(Error::NonCanonicalMap);
+                }
+                previous_key_bytes = Some(key_bytes.to_vec());
+                let value = parse_value(de, value_format)?;
+                entries.push((key, value));
+            }
+            Ok(Value::Map(entries))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_a_mix_of_types() {
+        let format = Format::Struct(Vec::from([
+            Format::U32,
+            Format::Seq(Box::new(Format::Bool)),
+            Format::Option(Box::new(Format::Bytes)),
+        ]));
+        let bytes = crate::to_bytes(&(7u32, Vec::from([true, false]), Some(Vec::from([1u8, 2])))).unwrap();
+
+        let value = trace_deserialize(&format, &bytes).unwrap();
+        assert_eq!(
+            value,
+            Value::Struct(Vec::from([
+                Value::U32(7),
+                Value::Seq(Vec::from([Value::Bool(true), Value::Bool(false)])),
+                Value::Option(Some(Box::new(Value::Bytes(Vec::from([1, 2]))))),
+            ]))
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_enum_variant() {
+        let format = Format::Enum(Vec::from([Format::U8, Format::U8]));
+        // Variant index 2 is out of range for a 2-variant enum.
+        let bytes = [2u8, 0u8];
+        assert_eq!(
+            trace_deserialize(&format, &bytes),
+            Err(Error::UnknownVariantIndex(2))
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_map() {
+        let format = Format::Map(Box::new(Format::U8), Box::new(Format::U8));
+        // length = 2, then two entries with the same key (1).
+        let bytes = [0x02, 0x01, 0x10, 0x01, 0x14];
+        assert_eq!(trace_deserialize(&format, &bytes), Err(Error::NonCanonicalMap));
+    }
+}